@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::{header, Client, Response, StatusCode};
+use serde::Deserialize;
+use tokio::sync::Semaphore;
+
+use crate::error::{Error, Result};
+use crate::source::ReleaseSource;
+use crate::{http, retry};
+use crate::Tag;
+
+/// A single page of tags, plus the URL of the next page, if any.
+pub(crate) struct Page {
+    pub(crate) tags: Vec<Tag>,
+    pub(crate) next: Option<String>,
+}
+
+/// A registry family that can list the tags for a repository.
+///
+/// `TagFetcher` drives this to walk every page of tags; each implementation
+/// only needs to know its own URL shape, response format, and pagination
+/// scheme.
+#[async_trait]
+pub(crate) trait Backend: Send {
+    /// Builds the URL for the first page of tags for `namespace/repo`.
+    fn initial_url(&self, namespace: &str, repo: &str) -> String;
+
+    /// Fetches and parses a single page of tags from `url`.
+    async fn fetch_page(&mut self, client: &Client, url: &str) -> Result<Page>;
+}
+
+/// Docker Hub's proprietary tag-listing API.
+pub(crate) struct DockerHubBackend {
+    host: String,
+}
+
+impl DockerHubBackend {
+    pub(crate) fn new(host: impl Into<String>) -> Self {
+        Self { host: host.into() }
+    }
+}
+
+#[derive(Deserialize)]
+struct DockerHubResults {
+    next: Option<String>,
+    results: Vec<Tag>,
+}
+
+#[async_trait]
+impl Backend for DockerHubBackend {
+    fn initial_url(&self, namespace: &str, repo: &str) -> String {
+        format!(
+            "https://{}/v2/namespaces/{}/repositories/{}/tags?page_size=100",
+            self.host, namespace, repo
+        )
+    }
+
+    async fn fetch_page(&mut self, client: &Client, url: &str) -> Result<Page> {
+        let resp = retry::send_with_retry(url, client.get(url)).await?;
+        let resp = resp.error_for_status().map_err(|source| Error::Http {
+            url: url.to_string(),
+            source,
+        })?;
+        let result: DockerHubResults = resp.json().await.map_err(|source| Error::Decode {
+            url: url.to_string(),
+            source,
+        })?;
+
+        Ok(Page {
+            tags: result.results,
+            next: result.next,
+        })
+    }
+}
+
+/// An OCI Distribution-spec registry (GHCR, Quay, private Harbor, ...).
+pub(crate) struct OciBackend {
+    host: String,
+    token: Option<String>,
+}
+
+impl OciBackend {
+    pub(crate) fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            token: None,
+        }
+    }
+
+    async fn send(&self, client: &Client, url: &str) -> Result<Response> {
+        let mut req = client.get(url);
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+
+        retry::send_with_retry(url, req).await
+    }
+
+    /// Performs the anonymous bearer-token dance described by the challenge
+    /// on a `401` response, and caches the resulting token.
+    async fn authenticate(&mut self, client: &Client, resp: &Response) -> Result<()> {
+        let url = resp.url().as_str().to_string();
+        let challenge = resp
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .ok_or_else(|| Error::Protocol {
+                url: url.clone(),
+                reason: "got 401 with no WWW-Authenticate header".to_string(),
+            })?
+            .to_str()
+            .map_err(|_| Error::Protocol {
+                url: url.clone(),
+                reason: "WWW-Authenticate header is not valid UTF-8".to_string(),
+            })?;
+        let params = parse_bearer_challenge(&url, challenge)?;
+        let realm = params.get("realm").ok_or_else(|| Error::Protocol {
+            url: url.clone(),
+            reason: "WWW-Authenticate challenge has no realm".to_string(),
+        })?;
+
+        let mut req = client.get(realm);
+        for key in ["service", "scope"] {
+            if let Some(value) = params.get(key) {
+                req = req.query(&[(key, value)]);
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            token: String,
+        }
+
+        let resp = retry::send_with_retry(realm, req).await?;
+        let resp = resp.error_for_status().map_err(|source| Error::Http {
+            url: realm.clone(),
+            source,
+        })?;
+        let token: TokenResponse = resp.json().await.map_err(|source| Error::Decode {
+            url: realm.clone(),
+            source,
+        })?;
+        self.token = Some(token.token);
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct OciTagList {
+    tags: Vec<String>,
+}
+
+#[async_trait]
+impl Backend for OciBackend {
+    fn initial_url(&self, namespace: &str, repo: &str) -> String {
+        format!(
+            "https://{}/v2/{}/{}/tags/list?n=100",
+            self.host, namespace, repo
+        )
+    }
+
+    async fn fetch_page(&mut self, client: &Client, url: &str) -> Result<Page> {
+        let mut resp = self.send(client, url).await?;
+        if resp.status() == StatusCode::UNAUTHORIZED {
+            self.authenticate(client, &resp).await?;
+            resp = self.send(client, url).await?;
+        }
+
+        let resp = resp.error_for_status().map_err(|source| Error::Http {
+            url: url.to_string(),
+            source,
+        })?;
+        let next = parse_link_header(resp.headers(), &self.host);
+        let result: OciTagList = resp.json().await.map_err(|source| Error::Decode {
+            url: url.to_string(),
+            source,
+        })?;
+
+        Ok(Page {
+            tags: result
+                .tags
+                .into_iter()
+                .map(|name| Tag { name })
+                .collect(),
+            next,
+        })
+    }
+}
+
+/// Parses a `WWW-Authenticate: Bearer realm="...",service="...",scope="..."`
+/// challenge into its key/value parameters.
+fn parse_bearer_challenge(url: &str, challenge: &str) -> Result<HashMap<String, String>> {
+    let rest = challenge.strip_prefix("Bearer ").ok_or_else(|| Error::Protocol {
+        url: url.to_string(),
+        reason: format!("unsupported WWW-Authenticate scheme: {challenge:?}"),
+    })?;
+
+    let mut params = HashMap::new();
+    for part in split_challenge_params(rest) {
+        let (key, value) = part.split_once('=').ok_or_else(|| Error::Protocol {
+            url: url.to_string(),
+            reason: format!("malformed WWW-Authenticate parameter: {part:?}"),
+        })?;
+        params.insert(
+            key.trim().to_string(),
+            value.trim().trim_matches('"').to_string(),
+        );
+    }
+
+    Ok(params)
+}
+
+/// Splits a challenge's comma-separated `key="value"` parameters, without
+/// breaking on commas inside a quoted value (e.g. a `scope` of
+/// `"repository:foo/bar:pull,push"`).
+fn split_challenge_params(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+
+    parts
+}
+
+/// Extracts the `rel="next"` target from a `Link` header, resolving
+/// host-relative targets against `host`.
+fn parse_link_header(headers: &header::HeaderMap, host: &str) -> Option<String> {
+    let target = http::next_link(headers)?;
+
+    Some(if let Some(path) = target.strip_prefix('/') {
+        format!("https://{}/{}", host, path)
+    } else {
+        target
+    })
+}
+
+/// Walks every page of tags for a repository on a single [`Backend`],
+/// acquiring a permit from `semaphore` before fetching each page and holding
+/// it across that page's requests (retries, and any token request), so that
+/// only a bounded number of requests are ever in flight across every
+/// repository being fetched.
+pub(crate) struct TagFetcher {
+    client: Client,
+    backend: Box<dyn Backend>,
+    semaphore: Arc<Semaphore>,
+    next: Option<String>,
+    tags: Vec<Tag>,
+}
+
+impl TagFetcher {
+    pub(crate) fn new(
+        backend: Box<dyn Backend>,
+        semaphore: Arc<Semaphore>,
+        namespace: &str,
+        repo: &str,
+    ) -> Self {
+        let next = Some(backend.initial_url(namespace, repo));
+
+        Self {
+            client: Client::new(),
+            backend,
+            semaphore,
+            next,
+            tags: Vec::new(),
+        }
+    }
+
+    async fn refill_cache(&mut self) -> Result<()> {
+        let url = match self.next.take() {
+            Some(url) => url,
+            None => {
+                return Ok(());
+            }
+        };
+
+        // Pagination within a repository is inherently sequential (the
+        // cursor is serial), but the permit keeps us from blowing the
+        // concurrency budget shared with every other repository.
+        let _permit = retry::acquire_permit(&self.semaphore).await;
+        let Page { tags, next } = self.backend.fetch_page(&self.client, &url).await?;
+        self.next = next;
+        self.tags = tags;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for TagFetcher {
+    async fn next_version(&mut self) -> Option<Result<String>> {
+        if self.tags.is_empty() {
+            if let Err(e) = self.refill_cache().await {
+                return Some(Err(e));
+            }
+        }
+
+        self.tags.pop().map(|tag| Ok(tag.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bearer_challenge_handles_commas_in_quoted_values() {
+        let params = parse_bearer_challenge(
+            "https://example.com",
+            r#"Bearer realm="https://auth.example.com/token",service="registry.example.com",scope="repository:foo/bar:pull,push""#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            params.get("realm").unwrap(),
+            "https://auth.example.com/token"
+        );
+        assert_eq!(params.get("service").unwrap(), "registry.example.com");
+        assert_eq!(
+            params.get("scope").unwrap(),
+            "repository:foo/bar:pull,push"
+        );
+    }
+
+    #[test]
+    fn parse_bearer_challenge_rejects_non_bearer_scheme() {
+        assert!(parse_bearer_challenge("https://example.com", "Basic realm=\"x\"").is_err());
+    }
+}
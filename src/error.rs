@@ -0,0 +1,31 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+pub(crate) type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong talking to a registry or release source.
+#[derive(Debug, Error, miette::Diagnostic)]
+pub(crate) enum Error {
+    #[error("request to {url} failed: {source}")]
+    Http {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("rate limited by {url}: {reason}")]
+    RateLimited { url: String, reason: String },
+
+    #[error("failed to decode response from {url}: {source}")]
+    Decode {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("unexpected status {status} from {url}")]
+    UnexpectedStatus { url: String, status: StatusCode },
+
+    #[error("malformed response from {url}: {reason}")]
+    Protocol { url: String, reason: String },
+}
@@ -0,0 +1,96 @@
+use reqwest::header::HeaderMap;
+
+/// Extracts the `rel="next"` target from a `Link` header (RFC 8288), as
+/// used for pagination by both the OCI Distribution spec and the GitHub
+/// API.
+pub(crate) fn next_link(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+
+    for part in split_link_values(link) {
+        let part = part.trim();
+        if !part.ends_with("rel=\"next\"") {
+            continue;
+        }
+
+        let start = part.find('<')? + 1;
+        let end = part.find('>')?;
+        return Some(part[start..end].to_string());
+    }
+
+    None
+}
+
+/// Splits a `Link` header into its individual link-values, without breaking
+/// on a comma that appears inside a `<URI-Reference>` (a URI may legally
+/// contain one, e.g. a registry's opaque pagination cursor).
+fn split_link_values(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_uri = false;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' => in_uri = true,
+            '>' => in_uri = false,
+            ',' if !in_uri => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, LINK};
+
+    #[test]
+    fn next_link_extracts_the_next_rel() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                r#"<https://api.github.com/repos/o/r/releases?page=2>; rel="next", <https://api.github.com/repos/o/r/releases?page=5>; rel="last""#,
+            ),
+        );
+
+        assert_eq!(
+            next_link(&headers).as_deref(),
+            Some("https://api.github.com/repos/o/r/releases?page=2")
+        );
+    }
+
+    #[test]
+    fn next_link_is_none_without_a_next_rel() {
+        let mut headers = HeaderMap::new();
+        headers.insert(LINK, HeaderValue::from_static(r#"<https://x>; rel="last""#));
+
+        assert_eq!(next_link(&headers), None);
+    }
+
+    #[test]
+    fn next_link_is_none_without_a_link_header() {
+        assert_eq!(next_link(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn next_link_handles_a_comma_inside_the_uri() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                r#"<https://example.com/v2/foo/tags/list?last=sha256:abc,def>; rel="next""#,
+            ),
+        );
+
+        assert_eq!(
+            next_link(&headers).as_deref(),
+            Some("https://example.com/v2/foo/tags/list?last=sha256:abc,def")
+        );
+    }
+}
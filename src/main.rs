@@ -1,28 +1,119 @@
-use std::{collections::BTreeMap, thread};
+mod backend;
+mod error;
+mod http;
+mod retry;
+mod source;
+
+use std::{collections::BTreeMap, sync::Arc};
 
-use chrono::{TimeZone, Utc};
 use clap::Parser;
 use miette::{IntoDiagnostic, Result};
-use reqwest::{blocking::Client, StatusCode};
-use semver::Version;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use backend::{Backend, DockerHubBackend, OciBackend, TagFetcher};
+use source::{GithubReleasesSource, ReleaseSource};
 
 #[derive(Parser)]
 struct Opt {
+    /// Where to discover published versions from. `github` ignores `--host`
+    /// and `--registry-type`, and treats `--namespace` as the repository
+    /// owner.
+    #[arg(long, value_enum, default_value = "docker")]
+    source: Source,
+
     #[arg(long, default_value = "hub.docker.com")]
     host: String,
 
+    /// Which registry API to speak. Defaults to Docker Hub's proprietary API
+    /// for `hub.docker.com`, and the OCI Distribution spec otherwise.
+    #[arg(long, value_enum)]
+    registry_type: Option<RegistryType>,
+
     #[arg(short, long)]
     namespace: String,
 
-    #[arg(short, long)]
-    repo: String,
+    /// Repository to fetch tags for. May be given multiple times to fetch
+    /// several repositories in one invocation. Each repository must be
+    /// named explicitly; there is no namespace-wide scan that discovers
+    /// repositories for you.
+    #[arg(short, long = "repo")]
+    repos: Vec<String>,
+
+    /// Maximum number of tag-list requests in flight at once, across every
+    /// repository being fetched.
+    #[arg(long, default_value_t = 16)]
+    concurrency: usize,
+
+    /// Only consider tags whose version satisfies this constraint, e.g.
+    /// ">=1.24, <1.27".
+    #[arg(long)]
+    constraint: Option<VersionReq>,
+
+    /// Print only the newest version satisfying `--constraint` as a bare
+    /// string, and exit non-zero if none is found. Useful for gating CI on
+    /// whether an acceptable upstream image exists.
+    #[arg(long)]
+    latest: bool,
+}
+
+impl Opt {
+    /// Validates the arguments needed before any network activity starts,
+    /// regardless of which `--source` was chosen.
+    fn validate(&self) -> Result<()> {
+        if self.repos.is_empty() {
+            miette::bail!("at least one --repo is required");
+        }
+
+        if self.concurrency == 0 {
+            miette::bail!("--concurrency must be at least 1");
+        }
+
+        Ok(())
+    }
+
+    fn backend(&self) -> Box<dyn Backend> {
+        let registry_type = self.registry_type.unwrap_or_else(|| {
+            if self.host == "hub.docker.com" {
+                RegistryType::DockerHub
+            } else {
+                RegistryType::Oci
+            }
+        });
+
+        match registry_type {
+            RegistryType::DockerHub => Box::new(DockerHubBackend::new(self.host.clone())),
+            RegistryType::Oci => Box::new(OciBackend::new(self.host.clone())),
+        }
+    }
+
+    /// Builds the release source selected by `--source` for a single
+    /// repository.
+    fn release_source(&self, semaphore: Arc<Semaphore>, repo: &str) -> Box<dyn ReleaseSource> {
+        match self.source {
+            Source::Docker => Box::new(TagFetcher::new(
+                self.backend(),
+                semaphore,
+                &self.namespace,
+                repo,
+            )),
+            Source::Github => Box::new(GithubReleasesSource::new(semaphore, &self.namespace, repo)),
+        }
+    }
 }
 
-#[derive(Deserialize)]
-struct Results {
-    next: Option<String>,
-    results: Vec<Tag>,
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum RegistryType {
+    DockerHub,
+    Oci,
+}
+
+/// A release source selectable via `--source`.
+#[derive(Copy, Clone, clap::ValueEnum)]
+enum Source {
+    Docker,
+    Github,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,7 +142,13 @@ struct VersionSet {
 }
 
 impl VersionSet {
-    fn maybe_insert(&mut self, mut version: Version) {
+    fn maybe_insert(&mut self, mut version: Version, constraint: Option<&VersionReq>) {
+        if let Some(constraint) = constraint {
+            if !constraint.matches(&version) {
+                return;
+            }
+        }
+
         self.versions
             .entry(MajorMinor {
                 major: version.major,
@@ -60,6 +157,13 @@ impl VersionSet {
             .and_modify(|value| *value = value.max(&mut version).clone())
             .or_insert(version);
     }
+
+    /// The single highest version in the set, if any. Keys are ordered by
+    /// major, then minor, and each entry holds the highest version for its
+    /// key, so the last entry in the map is always the overall maximum.
+    fn latest(&self) -> Option<&Version> {
+        self.versions.values().next_back()
+    }
 }
 
 impl Serialize for VersionSet {
@@ -71,106 +175,112 @@ impl Serialize for VersionSet {
     }
 }
 
-fn main() -> Result<()> {
+#[tokio::main]
+async fn main() -> Result<()> {
     let opt = Opt::parse();
+    opt.validate()?;
 
-    let mut versions = VersionSet::default();
-    let fetcher = TagFetcher::new(&opt.host, &opt.namespace, &opt.repo);
-    for tag_result in fetcher {
-        let name = tag_result?.name;
-        match lenient_semver::parse(&name) {
-            Ok(version) => versions.maybe_insert(version),
-            Err(_) => {
-                eprintln!("ignoring unparsable version {}", name);
+    let semaphore = Arc::new(Semaphore::new(opt.concurrency));
+
+    let tasks: Vec<_> = opt
+        .repos
+        .iter()
+        .map(|repo| {
+            let source = opt.release_source(Arc::clone(&semaphore), repo);
+            let namespace = opt.namespace.clone();
+            let repo = repo.clone();
+            let constraint = opt.constraint.clone();
+
+            tokio::spawn(async move {
+                let versions = fetch_versions(source, constraint.as_ref()).await;
+                (format!("{}/{}", namespace, repo), versions)
+            })
+        })
+        .collect();
+
+    let mut results: BTreeMap<String, VersionSet> = BTreeMap::new();
+    for task in tasks {
+        let (key, versions) = task.await.into_diagnostic()?;
+        results.insert(key, versions?);
+    }
+
+    if opt.latest {
+        return match results.values().filter_map(VersionSet::latest).max() {
+            Some(version) => {
+                println!("{}", version);
+                Ok(())
             }
-        }
+            None => {
+                if opt.constraint.is_some() {
+                    eprintln!("no tag satisfying the constraint was found");
+                } else {
+                    eprintln!("no parsable tags were found");
+                }
+                std::process::exit(1);
+            }
+        };
     }
 
     println!(
         "{}",
-        serde_json::to_string_pretty(&versions).into_diagnostic()?
+        serde_json::to_string_pretty(&results).into_diagnostic()?
     );
 
     Ok(())
 }
 
-struct TagFetcher {
-    client: Client,
-    next: Option<String>,
-    tags: Vec<Tag>,
-}
+/// Drains a single release source into a [`VersionSet`], ignoring any tag
+/// or release name that isn't a parsable version.
+async fn fetch_versions(
+    mut source: Box<dyn ReleaseSource>,
+    constraint: Option<&VersionReq>,
+) -> Result<VersionSet> {
+    let mut versions = VersionSet::default();
 
-impl TagFetcher {
-    fn new(host: &str, namespace: &str, repo: &str) -> Self {
-        Self {
-            client: Client::new(),
-            next: Some(format!(
-                "https://{}/v2/namespaces/{}/repositories/{}/tags?page_size=100",
-                host, namespace, repo
-            )),
-            tags: Vec::new(),
+    while let Some(version_result) = source.next_version().await {
+        let name = version_result?;
+        match lenient_semver::parse(&name) {
+            Ok(version) => versions.maybe_insert(version, constraint),
+            Err(_) => {
+                eprintln!("ignoring unparsable version {}", name);
+            }
         }
     }
 
-    fn refill_cache(&mut self) -> Result<()> {
-        let url = match self.next.take() {
-            Some(url) => url,
-            None => {
-                return Ok(());
-            }
-        };
+    Ok(versions)
+}
 
-        loop {
-            let resp = self.client.get(&url).send().into_diagnostic()?;
-
-            // If we got a 429, spin and try again.
-            if resp.status() == StatusCode::TOO_MANY_REQUESTS {
-                // Spin and try again based on the return header.
-                match resp.headers().get("x-retry-after") {
-                    Some(ts) => {
-                        let retry_after = Utc
-                            .timestamp_opt(
-                                ts.to_str().into_diagnostic()?.parse().into_diagnostic()?,
-                                0,
-                            )
-                            .single()
-                            .ok_or_else(|| {
-                                miette::miette!("could not parse x-retry-after {:?}", ts)
-                            })?;
-                        if let Ok(duration) = (retry_after - Utc::now()).to_std() {
-                            thread::sleep(duration);
-                        }
-
-                        continue;
-                    }
-                    None => {
-                        miette::bail!("got 429, but not x-retry-after header");
-                    }
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // Otherwise, handle any errors and return.
-            let resp = resp.error_for_status().into_diagnostic()?;
-            let result: Results = resp.json().into_diagnostic()?;
-            self.next = result.next;
-            self.tags = result.results;
-            break;
-        }
+    #[test]
+    fn maybe_insert_keeps_highest_patch_per_major_minor() {
+        let mut versions = VersionSet::default();
+        versions.maybe_insert(Version::parse("1.2.3").unwrap(), None);
+        versions.maybe_insert(Version::parse("1.2.1").unwrap(), None);
+        versions.maybe_insert(Version::parse("1.3.0").unwrap(), None);
 
-        Ok(())
+        assert_eq!(
+            versions.versions[&MajorMinor { major: 1, minor: 2 }].to_string(),
+            "1.2.3"
+        );
+        assert_eq!(versions.latest().unwrap().to_string(), "1.3.0");
     }
-}
 
-impl Iterator for TagFetcher {
-    type Item = Result<Tag>;
+    #[test]
+    fn maybe_insert_skips_versions_outside_the_constraint() {
+        let mut versions = VersionSet::default();
+        let constraint = VersionReq::parse(">=1.24.0, <1.27.0").unwrap();
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.tags.is_empty() {
-            if let Err(e) = self.refill_cache() {
-                return Some(Err(e));
-            }
-        }
+        versions.maybe_insert(Version::parse("1.27.0").unwrap(), Some(&constraint));
+        versions.maybe_insert(Version::parse("1.26.5").unwrap(), Some(&constraint));
+
+        assert_eq!(versions.latest().unwrap().to_string(), "1.26.5");
+    }
 
-        self.tags.pop().map(Ok)
+    #[test]
+    fn latest_is_none_for_an_empty_set() {
+        assert!(VersionSet::default().latest().is_none());
     }
 }
@@ -0,0 +1,211 @@
+use std::time::{Duration, SystemTime};
+
+use chrono::{TimeZone, Utc};
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::error::{Error, Result};
+
+/// Acquires a permit from `semaphore`, bounding the number of requests in
+/// flight at once. The tool never closes its semaphores, so this can't
+/// fail in practice.
+pub(crate) async fn acquire_permit(semaphore: &Semaphore) -> SemaphorePermit<'_> {
+    semaphore.acquire().await.expect("semaphore is never closed")
+}
+
+/// Maximum number of retries for a single request, including rate-limit
+/// retries, so a misbehaving registry can't keep us spinning forever.
+const MAX_RETRIES: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Sends `request`, retrying on connection errors, `5xx` responses, and
+/// `429` rate limiting, with exponential backoff and jitter between
+/// attempts. Any other response (including other 4xx statuses, which
+/// callers may want to handle themselves) is returned as-is.
+pub(crate) async fn send_with_retry(url: &str, request: RequestBuilder) -> Result<Response> {
+    let mut attempt: u32 = 0;
+
+    loop {
+        let req = request
+            .try_clone()
+            .expect("requests made by this tool have no streaming body and are always clonable");
+
+        let resp = match req.send().await {
+            Ok(resp) => resp,
+            Err(source) => {
+                attempt += 1;
+                if attempt > MAX_RETRIES || !is_retryable(&source) {
+                    return Err(Error::Http {
+                        url: url.to_string(),
+                        source,
+                    });
+                }
+
+                tokio::time::sleep(backoff(attempt)).await;
+                continue;
+            }
+        };
+
+        if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+            attempt += 1;
+            if attempt > MAX_RETRIES {
+                return Err(Error::RateLimited {
+                    url: url.to_string(),
+                    reason: format!("exceeded the retry budget of {} attempts", MAX_RETRIES),
+                });
+            }
+
+            sleep_for_rate_limit(&resp, attempt, url).await?;
+            continue;
+        }
+
+        if resp.status().is_server_error() {
+            attempt += 1;
+            if attempt > MAX_RETRIES {
+                return Err(Error::UnexpectedStatus {
+                    url: url.to_string(),
+                    status: resp.status(),
+                });
+            }
+
+            tokio::time::sleep(backoff(attempt)).await;
+            continue;
+        }
+
+        return Ok(resp);
+    }
+}
+
+fn is_retryable(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout()
+}
+
+/// `base * 2^attempt`, capped at `MAX_BACKOFF` and jittered so retrying
+/// clients don't all wake up in lockstep.
+fn backoff(attempt: u32) -> Duration {
+    let exp_ms = (BASE_BACKOFF.as_millis() as u64).saturating_mul(1u64 << attempt.min(16));
+    let capped_ms = exp_ms.min(MAX_BACKOFF.as_millis() as u64);
+    let jitter_ms = rand::thread_rng().gen_range(0..=capped_ms / 2);
+
+    Duration::from_millis(capped_ms / 2 + jitter_ms)
+}
+
+/// Sleeps for the delay requested by a `429` response, preferring the
+/// `x-retry-after` epoch header, then falling back to the standard
+/// `Retry-After` header (either delta-seconds or an HTTP-date), and finally
+/// to the regular backoff schedule if neither is present.
+async fn sleep_for_rate_limit(resp: &Response, attempt: u32, url: &str) -> Result<()> {
+    if let Some(duration) = parse_x_retry_after(resp.headers(), url)? {
+        tokio::time::sleep(duration).await;
+        return Ok(());
+    }
+
+    if let Some(duration) = parse_retry_after(resp.headers(), url)? {
+        tokio::time::sleep(duration).await;
+        return Ok(());
+    }
+
+    tokio::time::sleep(backoff(attempt)).await;
+    Ok(())
+}
+
+fn parse_x_retry_after(headers: &HeaderMap, url: &str) -> Result<Option<Duration>> {
+    let Some(value) = headers.get("x-retry-after") else {
+        return Ok(None);
+    };
+
+    let malformed = || Error::Protocol {
+        url: url.to_string(),
+        reason: format!("malformed x-retry-after header {value:?}"),
+    };
+
+    let ts: i64 = value
+        .to_str()
+        .map_err(|_| malformed())?
+        .parse()
+        .map_err(|_| malformed())?;
+    let retry_at = Utc.timestamp_opt(ts, 0).single().ok_or_else(malformed)?;
+
+    Ok(Some((retry_at - Utc::now()).to_std().unwrap_or_default()))
+}
+
+fn parse_retry_after(headers: &HeaderMap, url: &str) -> Result<Option<Duration>> {
+    let Some(value) = headers.get(reqwest::header::RETRY_AFTER) else {
+        return Ok(None);
+    };
+
+    let value = value.to_str().map_err(|_| Error::Protocol {
+        url: url.to_string(),
+        reason: "Retry-After header is not valid UTF-8".to_string(),
+    })?;
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Ok(Some(Duration::from_secs(secs)));
+    }
+
+    let at = httpdate::parse_http_date(value).map_err(|_| Error::Protocol {
+        url: url.to_string(),
+        reason: format!("malformed Retry-After header {value:?}"),
+    })?;
+
+    Ok(Some(
+        at.duration_since(SystemTime::now()).unwrap_or_default(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, RETRY_AFTER};
+
+    #[test]
+    fn backoff_stays_within_bounds() {
+        assert!(backoff(0) <= BASE_BACKOFF);
+        assert!(backoff(30) <= MAX_BACKOFF);
+    }
+
+    #[test]
+    fn parse_retry_after_accepts_delta_seconds() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+
+        let duration = parse_retry_after(&headers, "https://example.com")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(duration, Duration::from_secs(120));
+    }
+
+    #[test]
+    fn parse_retry_after_accepts_http_date() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2080 07:28:00 GMT"),
+        );
+
+        assert!(parse_retry_after(&headers, "https://example.com")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn parse_retry_after_rejects_garbage() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("not a valid value"));
+
+        assert!(parse_retry_after(&headers, "https://example.com").is_err());
+    }
+
+    #[test]
+    fn parse_retry_after_missing_header_is_none() {
+        let headers = HeaderMap::new();
+
+        assert!(parse_retry_after(&headers, "https://example.com")
+            .unwrap()
+            .is_none());
+    }
+}